@@ -0,0 +1,172 @@
+//! A `Drain` that publishes records over a ZeroMQ `PUB` socket. Gated behind the `zmq`
+//! feature so crates that don't need out-of-process log shipping don't pick up the
+//! dependency.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use slog::{Drain, Level, OwnedKVList, Record, Serializer};
+
+use zmq;
+
+/// Publishes each record as a line of text over a ZeroMQ `PUB` socket.
+///
+/// The line carries the timestamp, a short level string, the source file/line, the
+/// message, and all logger- and record-level KV pairs. Formatting happens into a
+/// per-drain reusable buffer to avoid an allocation per record.
+///
+/// `ZmqPub` does its own socket IO in `log`, so wrap it in `Async` to keep that off the
+/// hot path of the code doing the logging, the same way any other blocking drain is used
+/// with this crate:
+///
+/// ```ignore
+/// let zmq_pub = extra::ZmqPub::bind(&ctx, "tcp://127.0.0.1:5556")?;
+/// let (drain, _guard) = extra::Async::new(zmq_pub);
+/// ```
+pub struct ZmqPub {
+    socket: RefCell<zmq::Socket>,
+    buf: RefCell<Vec<u8>>,
+}
+
+impl ZmqPub {
+    /// Bind a `PUB` socket at `endpoint` and publish every record logged to it.
+    pub fn bind(ctx: &zmq::Context, endpoint: &str) -> zmq::Result<Self> {
+        let socket = ctx.socket(zmq::SocketType::PUB)?;
+        socket.bind(endpoint)?;
+        Ok(ZmqPub {
+            socket: RefCell::new(socket),
+            buf: RefCell::new(Vec::new()),
+        })
+    }
+}
+
+impl Drain for ZmqPub {
+    type Error = ::slog::Never;
+
+    fn log(&self, record: &Record, logger_values: &OwnedKVList) -> Result<(), ::slog::Never> {
+        let mut buf = self.buf.borrow_mut();
+        buf.clear();
+
+        write_timestamp(&mut buf);
+        let _ = write!(buf,
+                        " {} {}:{}: {}",
+                        level_str(record.level()),
+                        record.file(),
+                        record.line(),
+                        record.msg());
+
+        {
+            let mut ser = LineSerializer { buf: &mut buf };
+            let _ = logger_values.serialize(record, &mut ser);
+            for kv in record.values() {
+                let _ = kv.serialize(record, &mut ser);
+            }
+        }
+
+        // Framed as a single PUB message; lost-subscriber/slow-subscriber handling is
+        // ZeroMQ's job, not ours.
+        let _ = self.socket.borrow_mut().send(&buf[..], 0);
+
+        Ok(())
+    }
+}
+
+impl ::BatchDrain for ZmqPub {}
+
+fn write_timestamp(buf: &mut Vec<u8>) {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let _ = write!(buf, "{}.{:09}", now.as_secs(), now.subsec_nanos());
+}
+
+fn level_str(level: Level) -> &'static str {
+    match level {
+        Level::Critical => "CRIT",
+        Level::Error => "ERRO",
+        Level::Warning => "WARN",
+        Level::Info => "INFO",
+        Level::Debug => "DEBG",
+        Level::Trace => "TRCE",
+    }
+}
+
+/// Writes each KV pair as ` key=val` (a unit value as just ` key`) into a reusable buffer.
+struct LineSerializer<'a> {
+    buf: &'a mut Vec<u8>,
+}
+
+impl<'a> Serializer for LineSerializer<'a> {
+    fn emit_bool(&mut self, key: &str, val: bool) -> ::slog::Result {
+        let _ = write!(self.buf, " {}={}", key, val);
+        Ok(())
+    }
+    fn emit_unit(&mut self, key: &str) -> ::slog::Result {
+        let _ = write!(self.buf, " {}", key);
+        Ok(())
+    }
+    fn emit_none(&mut self, key: &str) -> ::slog::Result {
+        let _ = write!(self.buf, " {}=None", key);
+        Ok(())
+    }
+    fn emit_char(&mut self, key: &str, val: char) -> ::slog::Result {
+        let _ = write!(self.buf, " {}={}", key, val);
+        Ok(())
+    }
+    fn emit_u8(&mut self, key: &str, val: u8) -> ::slog::Result {
+        let _ = write!(self.buf, " {}={}", key, val);
+        Ok(())
+    }
+    fn emit_i8(&mut self, key: &str, val: i8) -> ::slog::Result {
+        let _ = write!(self.buf, " {}={}", key, val);
+        Ok(())
+    }
+    fn emit_u16(&mut self, key: &str, val: u16) -> ::slog::Result {
+        let _ = write!(self.buf, " {}={}", key, val);
+        Ok(())
+    }
+    fn emit_i16(&mut self, key: &str, val: i16) -> ::slog::Result {
+        let _ = write!(self.buf, " {}={}", key, val);
+        Ok(())
+    }
+    fn emit_u32(&mut self, key: &str, val: u32) -> ::slog::Result {
+        let _ = write!(self.buf, " {}={}", key, val);
+        Ok(())
+    }
+    fn emit_i32(&mut self, key: &str, val: i32) -> ::slog::Result {
+        let _ = write!(self.buf, " {}={}", key, val);
+        Ok(())
+    }
+    fn emit_f32(&mut self, key: &str, val: f32) -> ::slog::Result {
+        let _ = write!(self.buf, " {}={}", key, val);
+        Ok(())
+    }
+    fn emit_u64(&mut self, key: &str, val: u64) -> ::slog::Result {
+        let _ = write!(self.buf, " {}={}", key, val);
+        Ok(())
+    }
+    fn emit_i64(&mut self, key: &str, val: i64) -> ::slog::Result {
+        let _ = write!(self.buf, " {}={}", key, val);
+        Ok(())
+    }
+    fn emit_f64(&mut self, key: &str, val: f64) -> ::slog::Result {
+        let _ = write!(self.buf, " {}={}", key, val);
+        Ok(())
+    }
+    fn emit_usize(&mut self, key: &str, val: usize) -> ::slog::Result {
+        let _ = write!(self.buf, " {}={}", key, val);
+        Ok(())
+    }
+    fn emit_isize(&mut self, key: &str, val: isize) -> ::slog::Result {
+        let _ = write!(self.buf, " {}={}", key, val);
+        Ok(())
+    }
+    fn emit_str(&mut self, key: &str, val: &str) -> ::slog::Result {
+        let _ = write!(self.buf, " {}={}", key, val);
+        Ok(())
+    }
+    fn emit_arguments(&mut self, key: &str, val: &fmt::Arguments) -> ::slog::Result {
+        let _ = write!(self.buf, " {}={}", key, val);
+        Ok(())
+    }
+}